@@ -3,3 +3,72 @@ use std::env;
 pub fn jwt_secret() -> String {
     env::var("JWT_SECRET").expect("JWT_SECRET must be set")
 }
+
+/// Number of failed logins allowed within the attempt window before lockout.
+pub fn login_max_attempts() -> u32 {
+    env::var("LOGIN_MAX_ATTEMPTS").ok().and_then(|v| v.parse().ok()).unwrap_or(5)
+}
+
+/// Window (in seconds) over which failed logins are counted.
+pub fn login_attempt_window_secs() -> u64 {
+    env::var("LOGIN_ATTEMPT_WINDOW_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(900)
+}
+
+/// How long (in seconds) a locked out email or IP has to wait before trying again.
+pub fn login_lockout_secs() -> u64 {
+    env::var("LOGIN_LOCKOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(900)
+}
+
+/// Issuer (`iss`) set on and required of every JWT.
+pub fn jwt_issuer() -> String {
+    env::var("JWT_ISSUER").unwrap_or_else(|_| "rust-trading".to_string())
+}
+
+/// Audience (`aud`) set on and required of every JWT.
+pub fn jwt_audience() -> String {
+    env::var("JWT_AUDIENCE").unwrap_or_else(|_| "rust-trading".to_string())
+}
+
+/// When true, tokens issued before `iss`/`aud` were added (which carry neither claim)
+/// are still accepted. Tokens with a mismatched issuer or audience, or with only one
+/// of the two claims, are always rejected.
+/// Intended for the rollout period only.
+pub fn jwt_allow_legacy_tokens() -> bool {
+    env::var("JWT_ALLOW_LEGACY_TOKENS").map(|v| v == "true" || v == "1").unwrap_or(false)
+}
+
+/// Everything needed to issue and verify JWTs.
+pub struct JwtSettings {
+    pub secret: String,
+    pub issuer: String,
+    pub audience: String,
+    pub allow_legacy: bool,
+}
+
+/// Reads the JWT_* settings from the environment.
+pub fn jwt_settings() -> JwtSettings {
+    JwtSettings {
+        secret: jwt_secret(),
+        issuer: jwt_issuer(),
+        audience: jwt_audience(),
+        allow_legacy: jwt_allow_legacy_tokens(),
+    }
+}
+
+/// True when APP_ENV is set to "development". Dev-only conveniences such as seeding are gated on this.
+pub fn is_development() -> bool {
+    env::var("APP_ENV").map(|v| v == "development").unwrap_or(false)
+}
+
+/// When true, failed logins are also counted per client IP, not just per email.
+/// Off by default: behind a reverse proxy every client shares the proxy's address,
+/// so enable it only together with LOGIN_TRUST_PROXY_HEADERS or when clients connect directly.
+pub fn login_limit_by_ip() -> bool {
+    env::var("LOGIN_LIMIT_BY_IP").map(|v| v == "true" || v == "1").unwrap_or(false)
+}
+
+/// When true, the client IP used for login limiting is taken from the Forwarded /
+/// X-Forwarded-For headers. Only enable this behind a proxy that sets those headers.
+pub fn login_trust_proxy_headers() -> bool {
+    env::var("LOGIN_TRUST_PROXY_HEADERS").map(|v| v == "true" || v == "1").unwrap_or(false)
+}
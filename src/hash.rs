@@ -0,0 +1,87 @@
+use argon2::password_hash::{self, rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use std::fmt;
+
+/// Errors that can occur while hashing or verifying a password.
+#[derive(Debug)]
+pub enum HashError {
+    Argon2(password_hash::Error),
+    Bcrypt(bcrypt::BcryptError),
+}
+
+impl fmt::Display for HashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashError::Argon2(e) => write!(f, "argon2: {}", e),
+            HashError::Bcrypt(e) => write!(f, "bcrypt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HashError {}
+
+/// Hashes a password with argon2id. All new hashes use this algorithm.
+pub fn hash_password(password: &str) -> Result<String, HashError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(HashError::Argon2)
+}
+
+/// Returns true if the stored hash was produced by bcrypt ($2a$, $2b$, $2y$)
+/// and should be re-hashed with argon2 on the next successful login.
+pub fn is_legacy_hash(hashed_password: &str) -> bool {
+    hashed_password.starts_with("$2")
+}
+
+/// Verifies a password against a stored hash.
+/// The algorithm is detected from the hash prefix, so both legacy bcrypt
+/// hashes and argon2 PHC strings are accepted.
+pub fn verify_password(password: &str, hashed_password: &str) -> Result<bool, HashError> {
+    if is_legacy_hash(hashed_password) {
+        return bcrypt::verify(password, hashed_password).map_err(HashError::Bcrypt);
+    }
+
+    let parsed = PasswordHash::new(hashed_password).map_err(HashError::Argon2)?;
+    match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(HashError::Argon2(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argon2_round_trip() {
+        let hashed = hash_password("hunter2").unwrap();
+        assert!(hashed.starts_with("$argon2id$"));
+        assert!(!is_legacy_hash(&hashed));
+        assert!(verify_password("hunter2", &hashed).unwrap());
+    }
+
+    #[test]
+    fn wrong_password_is_not_an_error() {
+        let hashed = hash_password("hunter2").unwrap();
+        assert!(!verify_password("hunter3", &hashed).unwrap());
+    }
+
+    #[test]
+    fn bcrypt_hashes_are_verified_by_bcrypt() {
+        // Lowest bcrypt cost keeps the test fast.
+        let hashed = bcrypt::hash("hunter2", 4).unwrap();
+        assert!(hashed.starts_with("$2b$"));
+        assert!(is_legacy_hash(&hashed));
+        assert!(verify_password("hunter2", &hashed).unwrap());
+        assert!(!verify_password("hunter3", &hashed).unwrap());
+    }
+
+    #[test]
+    fn malformed_hashes_are_errors() {
+        assert!(verify_password("hunter2", "not-a-hash").is_err());
+        assert!(verify_password("hunter2", "$2b$garbage").is_err());
+    }
+}
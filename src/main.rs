@@ -2,12 +2,16 @@ use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
 use std::env;
 use sqlx::postgres::PgPoolOptions;
-use log::info;
+use log::{info, warn};
+use rate_limit::LoginLimiter;
 
 mod config;
 mod db;
+mod hash;
 mod models;
+mod rate_limit;
 mod routes;
+mod seed;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -24,18 +28,31 @@ async fn main() -> std::io::Result<()> {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     // Create PostgreSQL connection pool
-    let pool = PgPoolOptions::new()
+    let pool: db::DbPool = PgPoolOptions::new()
         .max_connections(5)
         .connect(&database_url)
         .await
         .expect("Failed to create pool");
 
+    // `--seed` creates a test user for local development. It is ignored outside APP_ENV=development.
+    if env::args().any(|arg| arg == "--seed") {
+        if config::is_development() {
+            seed::seed_test_user(&pool).await.expect("Failed to seed test user");
+        } else {
+            warn!("Ignoring --seed: APP_ENV is not development");
+        }
+    }
+
+    // Shared across workers so login attempts are counted globally
+    let login_limiter = web::Data::new(LoginLimiter::from_env());
+
     info!("Starting server at http://{}", bind_addr);
 
     // Start HTTP server with the connection pool and routes
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(login_limiter.clone())
             .configure(routes::init_routes)
     })
     .bind(bind_addr)?
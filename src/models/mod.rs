@@ -1,3 +1,3 @@
 pub mod user;
 
-pub use user::{User, UserResponse, SignupInput, LoginInput, ForgotPassInput};
\ No newline at end of file
+pub use user::{User, UserResponse, SignupInput, LoginInput, ForgotPassInput, DeleteAccountInput, IntrospectInput};
\ No newline at end of file
@@ -36,4 +36,14 @@ pub struct UserResponse {
 pub struct ForgotPassInput {
     pub email: String,
     pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountInput {
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectInput {
+    pub token: String,
 }
\ No newline at end of file
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::config;
+
+/// Login attempt bookkeeping for a single key (an email or an IP address).
+struct Attempts {
+    count: u32,
+    window_start: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// How often the whole map is swept for expired entries. Between sweeps, expiry is
+/// checked lazily on the keys being looked up, so a login never does O(n) work
+/// more than once per interval.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lookup map plus the time of the last full sweep, guarded by one mutex.
+struct State {
+    attempts: HashMap<String, Attempts>,
+    last_pruned: Instant,
+}
+
+/// In-memory brute-force protection for the login endpoint.
+/// Keys are counted independently, so both the target email and the
+/// client IP can be locked out.
+pub struct LoginLimiter {
+    state: Mutex<State>,
+    max_attempts: u32,
+    window: Duration,
+    lockout: Duration,
+}
+
+impl LoginLimiter {
+    pub fn new(max_attempts: u32, window: Duration, lockout: Duration) -> Self {
+        LoginLimiter {
+            state: Mutex::new(State {
+                attempts: HashMap::new(),
+                last_pruned: Instant::now(),
+            }),
+            max_attempts,
+            window,
+            lockout,
+        }
+    }
+
+    /// Builds a limiter from the LOGIN_* environment settings.
+    pub fn from_env() -> Self {
+        Self::new(
+            config::login_max_attempts(),
+            Duration::from_secs(config::login_attempt_window_secs()),
+            Duration::from_secs(config::login_lockout_secs()),
+        )
+    }
+
+    /// Reserves a login attempt against every key.
+    /// Returns false without counting anything if any key is locked out. Otherwise the
+    /// attempt is counted up front, before the credentials are checked, so concurrent
+    /// requests can't slip past the limit; a successful login should `reset` afterwards.
+    pub fn try_acquire(&self, keys: &[&str]) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        // Periodically drop expired entries so the map stays bounded.
+        if now.duration_since(state.last_pruned) >= PRUNE_INTERVAL {
+            state.attempts.retain(|_, a| !self.is_expired(a, now));
+            state.last_pruned = now;
+        }
+
+        // Entries for the keys at hand are expired lazily, whether or not a sweep ran.
+        for key in keys {
+            if state.attempts.get(*key).is_some_and(|a| self.is_expired(a, now)) {
+                state.attempts.remove(*key);
+            }
+        }
+
+        if keys.iter().any(|key| state.attempts.get(*key).is_some_and(|a| a.locked_until.is_some())) {
+            return false;
+        }
+
+        for key in keys {
+            let entry = state.attempts.entry(key.to_string()).or_insert(Attempts {
+                count: 0,
+                window_start: now,
+                locked_until: None,
+            });
+            entry.count += 1;
+            if entry.count >= self.max_attempts {
+                entry.locked_until = Some(now + self.lockout);
+            }
+        }
+
+        true
+    }
+
+    /// An entry is expired once its lockout has passed or, if not locked, once its window has.
+    fn is_expired(&self, attempts: &Attempts, now: Instant) -> bool {
+        match attempts.locked_until {
+            Some(until) => now >= until,
+            None => now.duration_since(attempts.window_start) >= self.window,
+        }
+    }
+
+    /// Clears the attempt count for the key, e.g. after a successful login.
+    pub fn reset(&self, key: &str) {
+        self.state.lock().unwrap().attempts.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMAIL: &str = "email:alice@example.com";
+    const IP: &str = "ip:10.0.0.1";
+
+    fn limiter(max_attempts: u32) -> LoginLimiter {
+        LoginLimiter::new(max_attempts, Duration::from_secs(60), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn locks_after_max_attempts() {
+        let limiter = limiter(3);
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(limiter.try_acquire(&[EMAIL]));
+        // The third attempt is still allowed but locks the key.
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(!limiter.try_acquire(&[EMAIL]));
+    }
+
+    #[test]
+    fn refused_attempts_are_not_counted_against_other_keys() {
+        let limiter = limiter(1);
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(!limiter.try_acquire(&[EMAIL, IP]));
+        // The IP key was not counted by the refused attempt.
+        assert!(limiter.try_acquire(&[IP]));
+    }
+
+    #[test]
+    fn reset_clears_lockout() {
+        let limiter = limiter(2);
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(!limiter.try_acquire(&[EMAIL]));
+
+        limiter.reset(EMAIL);
+        assert!(limiter.try_acquire(&[EMAIL]));
+    }
+
+    #[test]
+    fn zero_length_lockout_expires_immediately() {
+        let limiter = LoginLimiter::new(1, Duration::from_secs(60), Duration::ZERO);
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(limiter.try_acquire(&[EMAIL]));
+    }
+
+    #[test]
+    fn zero_length_window_never_accumulates() {
+        let limiter = LoginLimiter::new(2, Duration::ZERO, Duration::from_secs(60));
+        for _ in 0..10 {
+            assert!(limiter.try_acquire(&[EMAIL]));
+        }
+    }
+
+    #[test]
+    fn expired_entries_are_swept_after_the_prune_interval() {
+        let limiter = LoginLimiter::new(5, Duration::ZERO, Duration::from_secs(60));
+        assert!(limiter.try_acquire(&["email:a@example.com"]));
+        assert!(limiter.try_acquire(&["email:b@example.com"]));
+        assert_eq!(limiter.state.lock().unwrap().attempts.len(), 2);
+
+        // Pretend the last sweep was long ago; the next attempt sweeps both stale entries.
+        limiter.state.lock().unwrap().last_pruned = Instant::now() - PRUNE_INTERVAL;
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert_eq!(limiter.state.lock().unwrap().attempts.len(), 1);
+    }
+
+    #[test]
+    fn email_and_ip_are_counted_independently() {
+        let limiter = limiter(2);
+        assert!(limiter.try_acquire(&[EMAIL, IP]));
+        assert!(limiter.try_acquire(&["email:bob@example.com", IP]));
+        // The IP is now locked, but alice's email has only one attempt.
+        assert!(!limiter.try_acquire(&[EMAIL, IP]));
+        assert!(limiter.try_acquire(&[EMAIL]));
+        assert!(!limiter.try_acquire(&[EMAIL]));
+    }
+}
@@ -1,19 +1,22 @@
-use actix_web::{post, get, web, HttpResponse, Responder, HttpRequest};
+use actix_web::{post, get, delete, web, HttpResponse, Responder, HttpRequest};
+use actix_web::error::ErrorUnauthorized;
 use sqlx::PgPool;
-use crate::models::{SignupInput, LoginInput, User, UserResponse, ForgotPassInput};
+use crate::models::{SignupInput, LoginInput, User, UserResponse, ForgotPassInput, DeleteAccountInput, IntrospectInput};
 use uuid::Uuid;
-use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey, TokenData, errors::Error as JwtError};
+use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey, TokenData, errors::{Error as JwtError, ErrorKind as JwtErrorKind}};
 use serde::{Deserialize, Serialize};
-use crate::config;
+use log::info;
+use crate::config::{self, JwtSettings};
+use crate::rate_limit::LoginLimiter;
+use crate::hash::{hash_password, is_legacy_hash, verify_password};
 
 /// POST /signup
 /// Creates a new user with the provided email, name, and password.
 /// The paper_amount is set to 10,000 by default.
 #[post("/signup")]
 pub async fn signup(pool: web::Data<PgPool>, item: web::Json<SignupInput>) -> impl Responder {
-    // Hash the password using bcrypt
-    let hashed_password = match hash(&item.password, DEFAULT_COST) {
+    // Hash the password using argon2
+    let hashed_password = match hash_password(&item.password) {
         Ok(h) => h,
         Err(_) => return HttpResponse::InternalServerError().body("Error hashing password"),
     };
@@ -56,14 +59,84 @@ struct TokenResponse {
 /// JWT claims structure.
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
-    sub: String, // subject (user id)
-    exp: usize,  // expiration timestamp
+    sub: String,         // subject (user id)
+    exp: usize,          // expiration timestamp
+    iss: Option<String>, // issuer, absent on legacy tokens
+    aud: Option<String>, // audience, absent on legacy tokens
+}
+
+/// Issues a token for the given user, valid for 24 hours.
+fn issue_jwt(settings: &JwtSettings, user_id: Uuid) -> Result<String, JwtError> {
+    let claims = Claims {
+        sub: user_id.to_string(),
+        exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
+        iss: Some(settings.issuer.clone()),
+        aud: Some(settings.audience.clone()),
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(settings.secret.as_ref()))
+}
+
+/// Decodes and validates a token: signature, expiry, issuer and audience.
+/// Tokens missing both `iss` and `aud` are only accepted when legacy tokens are allowed.
+fn verify_jwt(settings: &JwtSettings, token: &str) -> Result<Claims, JwtError> {
+    let mut validation = Validation::default();
+    validation.set_issuer(&[&settings.issuer]);
+    validation.set_audience(&[&settings.audience]);
+    if !settings.allow_legacy {
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+    }
+
+    let token_data: TokenData<Claims> = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(settings.secret.as_ref()),
+        &validation,
+    )?;
+
+    // Legacy tokens carry neither claim. A token with only one of them was never issued
+    // by this service, so it is rejected even when legacy tokens are allowed.
+    match (&token_data.claims.iss, &token_data.claims.aud) {
+        (Some(_), None) => Err(JwtErrorKind::MissingRequiredClaim("aud".to_string()).into()),
+        (None, Some(_)) => Err(JwtErrorKind::MissingRequiredClaim("iss".to_string()).into()),
+        _ => Ok(token_data.claims),
+    }
+}
+
+/// Rate-limiter key for password attempts against an email.
+fn email_limit_key(email: &str) -> String {
+    format!("email:{}", email.to_lowercase())
 }
 
 /// POST /login
 /// Validates user credentials and returns a JWT token upon success.
+/// Repeated failures for the same email (or IP, if enabled) lock further attempts out for a cooldown period.
 #[post("/login")]
-pub async fn login(pool: web::Data<PgPool>, item: web::Json<LoginInput>) -> impl Responder {
+pub async fn login(
+    pool: web::Data<PgPool>,
+    limiter: web::Data<LoginLimiter>,
+    req: HttpRequest,
+    item: web::Json<LoginInput>,
+) -> impl Responder {
+    // Attempts are tracked per email and, if enabled, per client IP. Unknown emails are
+    // counted too, so a lockout never reveals whether an account exists.
+    let email_key = email_limit_key(&item.email);
+    let ip_key = if !config::login_limit_by_ip() {
+        None
+    } else if config::login_trust_proxy_headers() {
+        req.connection_info().realip_remote_addr().map(|ip| format!("ip:{}", ip))
+    } else {
+        req.peer_addr().map(|addr| format!("ip:{}", addr.ip()))
+    };
+
+    // The attempt is counted before the credentials are checked and cleared again on success.
+    let mut keys = vec![email_key.as_str()];
+    if let Some(key) = &ip_key {
+        keys.push(key);
+    }
+    if !limiter.try_acquire(&keys) {
+        return HttpResponse::TooManyRequests().body("Too many login attempts, try again later");
+    }
+
     // Retrieve the user from the database by email.
     let user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
         .bind(&item.email)
@@ -76,7 +149,7 @@ pub async fn login(pool: web::Data<PgPool>, item: web::Json<LoginInput>) -> impl
     };
 
     // Verify the provided password against the stored hashed password.
-    let valid = match verify(&item.password, &user.hashed_password) {
+    let valid = match verify_password(&item.password, &user.hashed_password) {
         Ok(valid) => valid,
         Err(_) => return HttpResponse::InternalServerError().body("Error verifying password"),
     };
@@ -85,15 +158,30 @@ pub async fn login(pool: web::Data<PgPool>, item: web::Json<LoginInput>) -> impl
         return HttpResponse::BadRequest().body("Invalid email or password");
     }
 
-    // Prepare JWT claims – here we set expiration 24 hours from now.
-    let jwt_secret = config::jwt_secret();
-    let my_claims = Claims {
-        sub: user.id.to_string(),
-        exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
-    };
+    // The IP counter is left to expire on its own so an attacker can't clear it
+    // by logging into an account they control.
+    limiter.reset(&email_key);
+
+    // Transparently migrate legacy bcrypt hashes to argon2 now that we have the plaintext.
+    // A failure here is logged but does not block the login.
+    if is_legacy_hash(&user.hashed_password) {
+        match hash_password(&item.password) {
+            Ok(new_hash) => {
+                let updated = sqlx::query("UPDATE users SET hashed_password = $1 WHERE id = $2")
+                    .bind(&new_hash)
+                    .bind(user.id)
+                    .execute(pool.get_ref())
+                    .await;
+                if let Err(e) = updated {
+                    eprintln!("Error migrating password hash: {:?}", e);
+                }
+            },
+            Err(e) => eprintln!("Error re-hashing password: {}", e),
+        }
+    }
 
     // Generate the token
-    let token = match encode(&Header::default(), &my_claims, &EncodingKey::from_secret(jwt_secret.as_ref())) {
+    let token = match issue_jwt(&config::jwt_settings(), user.id) {
         Ok(t) => t,
         Err(_) => return HttpResponse::InternalServerError().body("Error generating token"),
     };
@@ -101,38 +189,40 @@ pub async fn login(pool: web::Data<PgPool>, item: web::Json<LoginInput>) -> impl
     HttpResponse::Ok().json(TokenResponse { token })
 }
 
-/// GET /get-user
-/// Validates the JWT token from the `Authorization` header and returns the user info.
-#[get("/get-user")]
-pub async fn get_user(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+/// Validates the JWT token from the `Authorization` header and returns the user ID it was issued for.
+/// On failure the error carries the 401 response to send back to the client.
+fn authenticated_user_id(req: &HttpRequest) -> Result<Uuid, actix_web::Error> {
     // Extract token from Authorization header
-    let auth_header = req.headers().get("Authorization");
-    if auth_header.is_none() {
-        return HttpResponse::Unauthorized().body("Missing Authorization header");
-    }
-    let auth_str = auth_header.unwrap().to_str().unwrap_or("");
+    let auth_header = match req.headers().get("Authorization") {
+        Some(h) => h,
+        None => return Err(ErrorUnauthorized("Missing Authorization header")),
+    };
+    let auth_str = auth_header.to_str().unwrap_or("");
     if !auth_str.starts_with("Bearer ") {
-        return HttpResponse::Unauthorized().body("Invalid Authorization header");
+        return Err(ErrorUnauthorized("Invalid Authorization header"));
     }
     let token = auth_str.trim_start_matches("Bearer ").trim();
 
     // Decode the token to retrieve claims
-    let jwt_secret = config::jwt_secret();
-    let token_data: Result<TokenData<Claims>, JwtError> = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(jwt_secret.as_ref()),
-        &Validation::default(),
-    );
-
-    let claims = match token_data {
-        Ok(data) => data.claims,
-        Err(_) => return HttpResponse::Unauthorized().body("Invalid token"),
+    let claims = match verify_jwt(&config::jwt_settings(), token) {
+        Ok(claims) => claims,
+        Err(_) => return Err(ErrorUnauthorized("Invalid token")),
     };
 
     // Parse the user ID from the token's subject claim.
-    let user_id = match Uuid::parse_str(&claims.sub) {
+    match Uuid::parse_str(&claims.sub) {
+        Ok(uid) => Ok(uid),
+        Err(_) => Err(ErrorUnauthorized("Invalid token data")),
+    }
+}
+
+/// GET /get-user
+/// Validates the JWT token from the `Authorization` header and returns the user info.
+#[get("/get-user")]
+pub async fn get_user(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match authenticated_user_id(&req) {
         Ok(uid) => uid,
-        Err(_) => return HttpResponse::Unauthorized().body("Invalid token data"),
+        Err(e) => return HttpResponse::from_error(e),
     };
 
     // Fetch the user from the database using the parsed user ID.
@@ -151,6 +241,124 @@ pub async fn get_user(pool: web::Data<PgPool>, req: HttpRequest) -> impl Respond
             };
             HttpResponse::Ok().json(response)
         },
+        // A valid token whose account has since been deleted.
+        Err(sqlx::Error::RowNotFound) => HttpResponse::Unauthorized().body("User no longer exists"),
+        Err(_) => HttpResponse::InternalServerError().body("Error fetching user"),
+    }
+}
+
+/// DELETE /me
+/// Permanently deletes the authenticated user's account.
+/// The current password must be supplied in the body to confirm the deletion.
+#[delete("/me")]
+pub async fn delete_me(
+    pool: web::Data<PgPool>,
+    limiter: web::Data<LoginLimiter>,
+    req: HttpRequest,
+    item: web::Json<DeleteAccountInput>,
+) -> impl Responder {
+    let user_id = match authenticated_user_id(&req) {
+        Ok(uid) => uid,
+        Err(e) => return HttpResponse::from_error(e),
+    };
+
+    let user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool.get_ref())
+        .await;
+
+    let user = match user_result {
+        Ok(user) => user,
+        Err(_) => return HttpResponse::NotFound().body("User not found"),
+    };
+
+    // Password guesses here share the login lockout, so a stolen token can't be used to brute-force the password.
+    let email_key = email_limit_key(&user.email);
+    if !limiter.try_acquire(&[email_key.as_str()]) {
+        return HttpResponse::TooManyRequests().body("Too many password attempts, try again later");
+    }
+
+    // Re-verify the password before doing anything irreversible.
+    let valid = match verify_password(&item.password, &user.hashed_password) {
+        Ok(valid) => valid,
+        Err(_) => return HttpResponse::InternalServerError().body("Error verifying password"),
+    };
+
+    if !valid {
+        return HttpResponse::BadRequest().body("Invalid password");
+    }
+
+    limiter.reset(&email_key);
+
+    // The users table is currently the only place account data is stored.
+    let deleted = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user.id)
+        .execute(pool.get_ref())
+        .await;
+
+    match deleted {
+        Ok(result) => {
+            info!("Deleted account {}", user.id);
+            HttpResponse::Ok().json(DeleteAccountResponse { users_deleted: result.rows_affected() })
+        },
+        Err(e) => {
+            eprintln!("Error deleting user: {:?}", e);
+            HttpResponse::InternalServerError().body("Error deleting user")
+        }
+    }
+}
+
+/// Reports what was removed by an account deletion.
+#[derive(Serialize)]
+struct DeleteAccountResponse {
+    users_deleted: u64,
+}
+
+/// Token introspection result, loosely following RFC 7662.
+/// Inactive tokens serialize as just `{"active": false}`.
+#[derive(Serialize)]
+struct IntrospectResponse {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<usize>,
+}
+
+/// POST /introspect
+/// Reports whether a token is currently valid, so clients and gateways don't need to decode it themselves.
+/// Expired or invalid tokens produce `{"active": false}` rather than an error.
+/// Account deletion is the only revocation this service has, so a token whose user no longer exists is inactive too.
+#[post("/introspect")]
+pub async fn introspect(pool: web::Data<PgPool>, item: web::Json<IntrospectInput>) -> impl Responder {
+    let inactive = IntrospectResponse {
+        active: false,
+        sub: None,
+        exp: None,
+    };
+
+    let claims = match verify_jwt(&config::jwt_settings(), &item.token) {
+        Ok(claims) => claims,
+        Err(_) => return HttpResponse::Ok().json(inactive),
+    };
+
+    let user_id = match Uuid::parse_str(&claims.sub) {
+        Ok(uid) => uid,
+        Err(_) => return HttpResponse::Ok().json(inactive),
+    };
+
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(user_id)
+        .fetch_one(pool.get_ref())
+        .await;
+
+    match exists {
+        Ok(true) => HttpResponse::Ok().json(IntrospectResponse {
+            active: true,
+            sub: Some(claims.sub),
+            exp: Some(claims.exp),
+        }),
+        Ok(false) => HttpResponse::Ok().json(inactive),
         Err(_) => HttpResponse::InternalServerError().body("Error fetching user"),
     }
 }
@@ -166,7 +374,7 @@ pub async fn forgot_pass(pool: web::Data<PgPool>, item: web::Json<ForgotPassInpu
         Err(_) => return HttpResponse::BadRequest().body("User not found"),
     };
 
-    let hashed_password = match hash(&item.password, DEFAULT_COST) {
+    let hashed_password = match hash_password(&item.password) {
         Ok(h) => h,
         Err(_) => return HttpResponse::InternalServerError().body("Error hashing password"),
     };
@@ -177,7 +385,77 @@ pub async fn forgot_pass(pool: web::Data<PgPool>, item: web::Json<ForgotPassInpu
     .fetch_one(pool.get_ref()).await;
 
     match rec {
-        Ok(_) => return HttpResponse::Ok().json("Password has been reset"),
-        Err(_) => return  HttpResponse::InternalServerError().body("Server error")
+        Ok(_) => HttpResponse::Ok().json("Password has been reset"),
+        Err(_) => HttpResponse::InternalServerError().body("Server error")
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(allow_legacy: bool) -> JwtSettings {
+        JwtSettings {
+            secret: "test-secret".to_string(),
+            issuer: "rust-trading".to_string(),
+            audience: "rust-trading".to_string(),
+            allow_legacy,
+        }
+    }
+
+    fn token_with(iss: Option<&str>, aud: Option<&str>) -> String {
+        let claims = Claims {
+            sub: Uuid::new_v4().to_string(),
+            exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            iss: iss.map(str::to_string),
+            aud: aud.map(str::to_string),
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test-secret")).unwrap()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn issued_token_round_trips() {
+        let settings = settings(false);
+        let user_id = Uuid::new_v4();
+        let token = issue_jwt(&settings, user_id).unwrap();
+        let claims = verify_jwt(&settings, &token).unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+    }
+
+    #[test]
+    fn rejects_wrong_issuer() {
+        let token = token_with(Some("another-service"), Some("rust-trading"));
+        assert!(verify_jwt(&settings(false), &token).is_err());
+        assert!(verify_jwt(&settings(true), &token).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_audience() {
+        let token = token_with(Some("rust-trading"), Some("another-service"));
+        assert!(verify_jwt(&settings(false), &token).is_err());
+        assert!(verify_jwt(&settings(true), &token).is_err());
+    }
+
+    #[test]
+    fn rejects_only_one_of_issuer_and_audience() {
+        for allow_legacy in [false, true] {
+            let settings = settings(allow_legacy);
+            assert!(verify_jwt(&settings, &token_with(Some("rust-trading"), None)).is_err());
+            assert!(verify_jwt(&settings, &token_with(None, Some("rust-trading"))).is_err());
+        }
+    }
+
+    #[test]
+    fn legacy_tokens_accepted_only_when_allowed() {
+        let token = token_with(None, None);
+        assert!(verify_jwt(&settings(false), &token).is_err());
+        assert!(verify_jwt(&settings(true), &token).is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_secret() {
+        let mut settings = settings(false);
+        let token = issue_jwt(&settings, Uuid::new_v4()).unwrap();
+        settings.secret = "other-secret".to_string();
+        assert!(verify_jwt(&settings, &token).is_err());
+    }
+}
@@ -9,5 +9,7 @@ pub fn init_routes(cfg: &mut web::ServiceConfig) {
             .service(auth::login)
             .service(auth::get_user)
             .service(auth::forgot_pass)
+            .service(auth::delete_me)
+            .service(auth::introspect)
     );
 }
@@ -0,0 +1,32 @@
+use sqlx::PgPool;
+use std::error::Error;
+use log::info;
+use crate::hash::hash_password;
+
+const TEST_USER_EMAIL: &str = "test@example.com";
+const TEST_USER_NAME: &str = "Test User";
+const TEST_USER_PASSWORD: &str = "password123";
+
+/// Creates the development test user if it doesn't exist yet.
+/// Safe to run on every startup: an existing user with the same email is left untouched.
+pub async fn seed_test_user(pool: &PgPool) -> Result<(), Box<dyn Error>> {
+    let hashed_password = hash_password(TEST_USER_PASSWORD)?;
+
+    let result = sqlx::query(
+        "INSERT INTO users (email, name, hashed_password, paper_amount) VALUES ($1, $2, $3, $4) ON CONFLICT (email) DO NOTHING"
+    )
+    .bind(TEST_USER_EMAIL)
+    .bind(TEST_USER_NAME)
+    .bind(&hashed_password)
+    .bind(10000_i32)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        info!("Seeded test user {} / {}", TEST_USER_EMAIL, TEST_USER_PASSWORD);
+    } else {
+        info!("Test user {} already exists, skipping seed", TEST_USER_EMAIL);
+    }
+
+    Ok(())
+}